@@ -0,0 +1,87 @@
+use anyhow::{Context, Result, bail};
+use rdev::Key;
+use serde::Deserialize;
+use std::{env, fs};
+
+/// Runtime configuration, loaded from a TOML file whose path is given as
+/// the first CLI argument (falling back to `Config::default()`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Address the HTTP/WebSocket server binds to, e.g. `"127.0.0.1:7878"`.
+    #[serde(default = "default_bind_addr")]
+    pub bind_addr: String,
+    /// Key that toggles recording on and off.
+    #[serde(default = "default_record_key", deserialize_with = "deserialize_key")]
+    pub record_key: Key,
+    /// Key that cancels the note currently being recorded.
+    #[serde(default = "default_cancel_key", deserialize_with = "deserialize_key")]
+    pub cancel_key: Key,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            bind_addr: default_bind_addr(),
+            record_key: default_record_key(),
+            cancel_key: default_cancel_key(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file named by the first CLI argument, or falls
+    /// back to `Config::default()` if no path was given. A path that
+    /// cannot be read or parsed is returned as an error.
+    pub fn load() -> Result<Self> {
+        let Some(path) = env::args().nth(1) else {
+            return Ok(Config::default());
+        };
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("reading config file {path}"))?;
+        toml::from_str(&contents).with_context(|| format!("parsing config file {path}"))
+    }
+}
+
+fn default_bind_addr() -> String {
+    "127.0.0.1:7878".to_string()
+}
+
+fn default_record_key() -> Key {
+    Key::F9
+}
+
+fn default_cancel_key() -> Key {
+    Key::Escape
+}
+
+fn deserialize_key<'de, D>(deserializer: D) -> Result<Key, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    key_from_name(&name).map_err(serde::de::Error::custom)
+}
+
+/// Parses the small set of key names we expect to see in a config file.
+/// Extend this list as new hotkeys need to be configurable.
+fn key_from_name(name: &str) -> Result<Key> {
+    Ok(match name {
+        "F1" => Key::F1,
+        "F2" => Key::F2,
+        "F3" => Key::F3,
+        "F4" => Key::F4,
+        "F5" => Key::F5,
+        "F6" => Key::F6,
+        "F7" => Key::F7,
+        "F8" => Key::F8,
+        "F9" => Key::F9,
+        "F10" => Key::F10,
+        "F11" => Key::F11,
+        "F12" => Key::F12,
+        "Escape" => Key::Escape,
+        "Pause" => Key::Pause,
+        "ScrollLock" => Key::ScrollLock,
+        other => bail!("unrecognized key name in config: {other}"),
+    })
+}
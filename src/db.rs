@@ -0,0 +1,84 @@
+use crate::Note;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{
+    SqlitePool,
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+};
+use std::str::FromStr;
+
+/// Async wrapper around the SQLite pool backing persisted notes.
+///
+/// Cloning is cheap: `SqlitePool` is an `Arc` internally, so every clone
+/// shares the same underlying connections.
+#[derive(Clone)]
+pub struct Db {
+    pool: SqlitePool,
+}
+
+impl Db {
+    /// Opens (creating if necessary) the database at `path` and runs the
+    /// migrations in `migrations/` against it.
+    pub async fn connect(path: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{path}"))?
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(Self { pool })
+    }
+
+    /// Loads every persisted note, oldest first.
+    pub async fn load_notes(&self) -> Result<Vec<Note>> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT text, recorded_at FROM notes ORDER BY id ASC")
+                .fetch_all(&self.pool)
+                .await?;
+        rows.into_iter()
+            .map(|(text, recorded_at)| {
+                let recorded_at = DateTime::parse_from_rfc3339(&recorded_at)?.with_timezone(&Utc);
+                Ok(Note { text, recorded_at })
+            })
+            .collect()
+    }
+
+    /// Appends a completed note to the `notes` table.
+    pub async fn insert_note(&self, text: &str, recorded_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query("INSERT INTO notes (text, recorded_at) VALUES (?, ?)")
+            .bind(text)
+            .bind(recorded_at.to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes every row in the `notes` table.
+    pub async fn clear_notes(&self) -> Result<()> {
+        sqlx::query("DELETE FROM notes").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Deletes the note at `index` in the same oldest-first ordering
+    /// `load_notes` uses, i.e. the row that is the `index`-th by id.
+    pub async fn delete_note_at(&self, index: usize) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM notes WHERE id = (SELECT id FROM notes ORDER BY id ASC LIMIT 1 OFFSET ?)",
+        )
+        .bind(index as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Replaces the text of the note at `index`, using the same ordering
+    /// as `delete_note_at`.
+    pub async fn update_note_at(&self, index: usize, text: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE notes SET text = ? WHERE id = (SELECT id FROM notes ORDER BY id ASC LIMIT 1 OFFSET ?)",
+        )
+        .bind(text)
+        .bind(index as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -1,40 +1,126 @@
+mod config;
+mod db;
+mod metrics;
+
 use anyhow::Result;
 use axum::{
     Json, Router,
-    extract::State,
+    extract::{
+        Path, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
     http::StatusCode,
     response::Html,
-    routing::{get, post},
+    routing::{delete, get, post, put},
 };
+use chrono::{DateTime, Utc};
+use config::Config;
+use db::Db;
+use metrics::Metrics;
 use rdev::{Event, EventType, Key, listen};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
     sync::{Arc, Mutex},
     thread,
 };
-use tokio::net::TcpListener;
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+};
 
 static INDEX_HTML: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/index.html"));
 
+/// Path to the SQLite database file, created in the current working
+/// directory on first run.
+const DB_PATH: &str = "db.sqlite";
+
+/// Capacity of the snapshot broadcast channel. Generous enough that a
+/// momentarily slow client doesn't miss updates between two key events.
+const SNAPSHOT_CHANNEL_CAPACITY: usize = 32;
+
+/// A completed note, stamped with the moment recording stopped.
+#[derive(Debug, Clone, Serialize)]
+struct Note {
+    text: String,
+    recorded_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Default)]
 struct NotesState {
     recording: bool,
     current_note: String,
-    notes: Vec<String>,
+    notes: Vec<Note>,
+    /// Whether either Shift key is currently held down.
+    shift_held: bool,
+    /// Caps Lock toggles independently of Shift and only affects letters.
+    caps_lock: bool,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Serialize)]
 struct NotesSnapshot {
     recording: bool,
     current_note: String,
-    notes: Vec<String>,
+    notes: Vec<Note>,
+}
+
+impl From<&NotesState> for NotesSnapshot {
+    fn from(s: &NotesState) -> Self {
+        NotesSnapshot {
+            recording: s.recording,
+            current_note: s.current_note.clone(),
+            notes: s.notes.clone(),
+        }
+    }
 }
 
-type SharedState = Arc<Mutex<NotesState>>;
+struct AppState {
+    notes: Mutex<NotesState>,
+    snapshots: broadcast::Sender<NotesSnapshot>,
+    db: Db,
+    /// Hands completed notes to the async task that persists them, so the
+    /// blocking keyboard listener thread never waits on SQLite.
+    persist_tx: mpsc::UnboundedSender<Note>,
+    config: Config,
+    metrics: Metrics,
+}
+
+type SharedState = Arc<AppState>;
+
+/// Publishes the current state to every subscribed WebSocket client.
+/// Dropped snapshots (no subscribers) are not an error.
+fn broadcast_snapshot(state: &AppState, s: &NotesState) {
+    let _ = state.snapshots.send(NotesSnapshot::from(s));
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let state: SharedState = Arc::new(Mutex::new(NotesState::default()));
+    let config = Config::load()?;
+    let db = Db::connect(DB_PATH).await?;
+    let notes = db.load_notes().await?;
+
+    let (snapshots, _) = broadcast::channel(SNAPSHOT_CHANNEL_CAPACITY);
+    let (persist_tx, mut persist_rx) = mpsc::unbounded_channel::<Note>();
+    let bind_addr = config.bind_addr.clone();
+    let state: SharedState = Arc::new(AppState {
+        notes: Mutex::new(NotesState {
+            notes,
+            ..NotesState::default()
+        }),
+        snapshots,
+        db: db.clone(),
+        persist_tx,
+        config,
+        metrics: Metrics::new()?,
+    });
+
+    // Persist completed notes as they arrive from the keyboard listener.
+    tokio::spawn(async move {
+        while let Some(note) = persist_rx.recv().await {
+            if let Err(e) = db.insert_note(&note.text, note.recorded_at).await {
+                eprintln!("Error persisting note: {:?}", e);
+            }
+        }
+    });
 
     // Spawn keyboard listener
     {
@@ -51,27 +137,32 @@ async fn main() -> Result<()> {
         .route("/", get(index))
         .route("/state", get(get_state))
         .route("/clear", post(clear_notes))
+        .route("/ws", get(ws_handler))
+        .route("/metrics", get(get_metrics))
+        .route("/notes/{index}", delete(delete_note).put(update_note))
         .with_state(state);
-    let listener = TcpListener::bind("127.0.0.1:7878").await?;
-    println!("Listening on 127.0.0.1:7878");
+    let listener = TcpListener::bind(&bind_addr).await?;
+    println!("Listening on {bind_addr}");
     axum::serve(listener, app).await?;
 
     Ok(())
 }
 
 async fn get_state(State(state): State<SharedState>) -> Json<NotesSnapshot> {
-    let s = state.lock().unwrap();
-    let snapshot = NotesSnapshot {
-        recording: s.recording,
-        current_note: s.current_note.clone(),
-        notes: s.notes.clone(),
-    };
-    Json(snapshot)
+    let s = state.notes.lock().unwrap();
+    Json(NotesSnapshot::from(&*s))
 }
 
 async fn clear_notes(State(state): State<SharedState>) -> StatusCode {
-    let mut s = state.lock().unwrap();
+    if let Err(e) = state.db.clear_notes().await {
+        eprintln!("Error clearing persisted notes: {:?}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    let mut s = state.notes.lock().unwrap();
+    state.metrics.notes_cleared_total.inc_by(s.notes.len() as u64);
     s.notes.clear();
+    broadcast_snapshot(&state, &s);
     StatusCode::NO_CONTENT
 }
 
@@ -79,112 +170,249 @@ async fn index() -> Html<&'static str> {
     Html(INDEX_HTML)
 }
 
+#[derive(Deserialize)]
+struct UpdateNoteRequest {
+    text: String,
+}
+
+async fn delete_note(State(state): State<SharedState>, Path(index): Path<usize>) -> StatusCode {
+    {
+        let s = state.notes.lock().unwrap();
+        if index >= s.notes.len() {
+            return StatusCode::NOT_FOUND;
+        }
+    }
+
+    if let Err(e) = state.db.delete_note_at(index).await {
+        eprintln!("Error deleting persisted note: {:?}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    let mut s = state.notes.lock().unwrap();
+    if index < s.notes.len() {
+        s.notes.remove(index);
+    }
+    broadcast_snapshot(&state, &s);
+    StatusCode::NO_CONTENT
+}
+
+async fn update_note(
+    State(state): State<SharedState>,
+    Path(index): Path<usize>,
+    Json(body): Json<UpdateNoteRequest>,
+) -> StatusCode {
+    {
+        let s = state.notes.lock().unwrap();
+        if index >= s.notes.len() {
+            return StatusCode::NOT_FOUND;
+        }
+    }
+
+    if let Err(e) = state.db.update_note_at(index, &body.text).await {
+        eprintln!("Error updating persisted note: {:?}", e);
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    }
+
+    let mut s = state.notes.lock().unwrap();
+    if let Some(note) = s.notes.get_mut(index) {
+        note.text = body.text;
+    }
+    broadcast_snapshot(&state, &s);
+    StatusCode::NO_CONTENT
+}
+
+async fn get_metrics(State(state): State<SharedState>) -> Result<String, StatusCode> {
+    state
+        .metrics
+        .render()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<SharedState>,
+) -> axum::response::Response {
+    ws.on_upgrade(move |socket| ws_connection(socket, state))
+}
+
+/// Forwards every published `NotesSnapshot` to one connected client until it
+/// disconnects. A client that falls behind skips the snapshots it missed and
+/// carries on with the next one.
+async fn ws_connection(mut socket: WebSocket, state: SharedState) {
+    let mut rx = state.snapshots.subscribe();
+
+    loop {
+        let snapshot = match rx.recv().await {
+            Ok(snapshot) => snapshot,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let Ok(text) = serde_json::to_string(&snapshot) else {
+            continue;
+        };
+        if socket.send(Message::Text(text.into())).await.is_err() {
+            break;
+        }
+    }
+}
+
 fn handle_key_event(event: Event, state: &SharedState) {
-    if let EventType::KeyPress(key) = event.event_type {
-        let mut s = state.lock().unwrap();
+    match event.event_type {
+        EventType::KeyPress(key) => handle_key_press(key, state),
+        EventType::KeyRelease(key) => handle_key_release(key, state),
+        _ => {}
+    }
+}
 
-        match key {
-            // Record/Stop Recording
-            Key::F9 => {
-                s.recording = !s.recording;
-                if !s.recording {
-                    let note = s.current_note.clone();
-
-                    if !note.trim().is_empty() {
-                        s.notes.push(note);
-                    }
-                    s.current_note.clear();
-                }
+/// Shift reverts to unshifted the instant it's released, even mid-note.
+fn handle_key_release(key: Key, state: &SharedState) {
+    if matches!(key, Key::ShiftLeft | Key::ShiftRight) {
+        state.notes.lock().unwrap().shift_held = false;
+    }
+}
+
+fn handle_key_press(key: Key, state: &SharedState) {
+    state.metrics.keypresses_total.inc();
+
+    let mut s = state.notes.lock().unwrap();
+
+    // Modifiers are tracked but never recorded as characters themselves.
+    match key {
+        Key::ShiftLeft | Key::ShiftRight => {
+            s.shift_held = true;
+            return;
+        }
+        Key::CapsLock => {
+            s.caps_lock = !s.caps_lock;
+            return;
+        }
+        _ => {}
+    }
+
+    if key == state.config.record_key {
+        // Record/Stop Recording
+        s.recording = !s.recording;
+        if !s.recording {
+            let note = s.current_note.clone();
+
+            if !note.trim().is_empty() {
+                let note = Note {
+                    text: note,
+                    recorded_at: Utc::now(),
+                };
+                state.metrics.notes_recorded_total.inc();
+                let _ = state.persist_tx.send(note.clone());
+                s.notes.push(note);
             }
-            // Cancel current recording
-            Key::Escape => {
-                s.recording = false;
-                s.current_note.clear();
+            s.current_note.clear();
+        }
+    } else if key == state.config.cancel_key {
+        // Cancel current recording
+        s.recording = false;
+        s.current_note.clear();
+    } else {
+        if !s.recording {
+            return;
+        }
+
+        match key {
+            // Basic functions
+            Key::Return => s.current_note.push('\n'),
+            Key::Space => s.current_note.push(' '),
+            Key::Backspace => {
+                s.current_note.pop();
             }
-            _ => {
-                if !s.recording {
-                    return;
-                }
 
-                match key {
-                    // Basic functions
-                    Key::Return => s.current_note.push('\n'),
-                    Key::Space => s.current_note.push(' '),
-                    Key::Backspace => {
-                        s.current_note.pop();
-                    }
-
-                    // Arrow keys
-                    Key::UpArrow => s.current_note.push('↑'),
-                    Key::DownArrow => s.current_note.push('↓'),
-                    Key::LeftArrow => s.current_note.push('←'),
-                    Key::RightArrow => s.current_note.push('→'),
-
-                    // Regular keys
-                    k => {
-                        if let Some(ch) = key_to_char(k) {
-                            s.current_note.push(ch);
-                        }
-                    }
+            // Arrow keys
+            Key::UpArrow => s.current_note.push('↑'),
+            Key::DownArrow => s.current_note.push('↓'),
+            Key::LeftArrow => s.current_note.push('←'),
+            Key::RightArrow => s.current_note.push('→'),
+
+            // Regular keys
+            k => {
+                if let Some(ch) = key_to_char(k, s.shift_held, s.caps_lock) {
+                    s.current_note.push(ch);
                 }
             }
         }
     }
+
+    state.metrics.recording_active.set(s.recording as i64);
+    state.metrics.current_note_len.set(s.current_note.chars().count() as i64);
+    broadcast_snapshot(state, &s);
 }
 
-fn key_to_char(key: Key) -> Option<char> {
+/// Maps a physical key to the character it produces, given the current
+/// modifier state. Caps Lock only affects letter case; Shift affects both
+/// letter case (XOR'd with Caps Lock) and the symbol row.
+fn key_to_char(key: Key, shift_held: bool, caps_lock: bool) -> Option<char> {
     use Key::*;
 
+    let letters_upper = shift_held ^ caps_lock;
+
     Some(match key {
-        KeyA => 'a',
-        KeyB => 'b',
-        KeyC => 'c',
-        KeyD => 'd',
-        KeyE => 'e',
-        KeyF => 'f',
-        KeyG => 'g',
-        KeyH => 'h',
-        KeyI => 'i',
-        KeyJ => 'j',
-        KeyK => 'k',
-        KeyL => 'l',
-        KeyM => 'm',
-        KeyN => 'n',
-        KeyO => 'o',
-        KeyP => 'p',
-        KeyQ => 'q',
-        KeyR => 'r',
-        KeyS => 's',
-        KeyT => 't',
-        KeyU => 'u',
-        KeyV => 'v',
-        KeyW => 'w',
-        KeyX => 'x',
-        KeyY => 'y',
-        KeyZ => 'z',
-
-        Num0 | Kp0 => '0',
-        Num1 | Kp1 => '1',
-        Num2 | Kp2 => '2',
-        Num3 | Kp3 => '3',
-        Num4 | Kp4 => '4',
-        Num5 | Kp5 => '5',
-        Num6 | Kp6 => '6',
-        Num7 | Kp7 => '7',
-        Num8 | Kp8 => '8',
-        Num9 | Kp9 => '9',
-
-        Minus => '-',
-        Equal => '=',
-        LeftBracket => '[',
-        RightBracket => ']',
-        SemiColon => ';',
-        Quote => '\'',
-        BackQuote => '`',
-        BackSlash => '\\',
-        Comma => ',',
-        Dot => '.',
-        Slash => '/',
+        KeyA => if letters_upper { 'A' } else { 'a' },
+        KeyB => if letters_upper { 'B' } else { 'b' },
+        KeyC => if letters_upper { 'C' } else { 'c' },
+        KeyD => if letters_upper { 'D' } else { 'd' },
+        KeyE => if letters_upper { 'E' } else { 'e' },
+        KeyF => if letters_upper { 'F' } else { 'f' },
+        KeyG => if letters_upper { 'G' } else { 'g' },
+        KeyH => if letters_upper { 'H' } else { 'h' },
+        KeyI => if letters_upper { 'I' } else { 'i' },
+        KeyJ => if letters_upper { 'J' } else { 'j' },
+        KeyK => if letters_upper { 'K' } else { 'k' },
+        KeyL => if letters_upper { 'L' } else { 'l' },
+        KeyM => if letters_upper { 'M' } else { 'm' },
+        KeyN => if letters_upper { 'N' } else { 'n' },
+        KeyO => if letters_upper { 'O' } else { 'o' },
+        KeyP => if letters_upper { 'P' } else { 'p' },
+        KeyQ => if letters_upper { 'Q' } else { 'q' },
+        KeyR => if letters_upper { 'R' } else { 'r' },
+        KeyS => if letters_upper { 'S' } else { 's' },
+        KeyT => if letters_upper { 'T' } else { 't' },
+        KeyU => if letters_upper { 'U' } else { 'u' },
+        KeyV => if letters_upper { 'V' } else { 'v' },
+        KeyW => if letters_upper { 'W' } else { 'w' },
+        KeyX => if letters_upper { 'X' } else { 'x' },
+        KeyY => if letters_upper { 'Y' } else { 'y' },
+        KeyZ => if letters_upper { 'Z' } else { 'z' },
+
+        Kp0 => '0',
+        Kp1 => '1',
+        Kp2 => '2',
+        Kp3 => '3',
+        Kp4 => '4',
+        Kp5 => '5',
+        Kp6 => '6',
+        Kp7 => '7',
+        Kp8 => '8',
+        Kp9 => '9',
+
+        Num0 => if shift_held { ')' } else { '0' },
+        Num1 => if shift_held { '!' } else { '1' },
+        Num2 => if shift_held { '@' } else { '2' },
+        Num3 => if shift_held { '#' } else { '3' },
+        Num4 => if shift_held { '$' } else { '4' },
+        Num5 => if shift_held { '%' } else { '5' },
+        Num6 => if shift_held { '^' } else { '6' },
+        Num7 => if shift_held { '&' } else { '7' },
+        Num8 => if shift_held { '*' } else { '8' },
+        Num9 => if shift_held { '(' } else { '9' },
+
+        Minus => if shift_held { '_' } else { '-' },
+        Equal => if shift_held { '+' } else { '=' },
+        LeftBracket => if shift_held { '{' } else { '[' },
+        RightBracket => if shift_held { '}' } else { ']' },
+        SemiColon => if shift_held { ':' } else { ';' },
+        Quote => if shift_held { '"' } else { '\'' },
+        BackQuote => if shift_held { '~' } else { '`' },
+        BackSlash => if shift_held { '|' } else { '\\' },
+        Comma => if shift_held { '<' } else { ',' },
+        Dot => if shift_held { '>' } else { '.' },
+        Slash => if shift_held { '?' } else { '/' },
 
         _ => return None,
     })
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use prometheus::{Encoder, IntCounter, IntGauge, Registry, TextEncoder};
+
+/// Operational counters and gauges exposed on `/metrics`.
+pub struct Metrics {
+    registry: Registry,
+    pub notes_recorded_total: IntCounter,
+    pub notes_cleared_total: IntCounter,
+    pub keypresses_total: IntCounter,
+    pub recording_active: IntGauge,
+    pub current_note_len: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Result<Self> {
+        let registry = Registry::new();
+
+        let notes_recorded_total = IntCounter::new(
+            "notes_recorded_total",
+            "Total number of notes completed and recorded",
+        )?;
+        let notes_cleared_total = IntCounter::new(
+            "notes_cleared_total",
+            "Total number of notes removed via /clear",
+        )?;
+        let keypresses_total = IntCounter::new(
+            "keypresses_total",
+            "Total number of keypress events processed",
+        )?;
+        let recording_active = IntGauge::new(
+            "recording_active",
+            "Whether a note is currently being recorded (1) or not (0)",
+        )?;
+        let current_note_len = IntGauge::new(
+            "current_note_len",
+            "Length in characters of the note currently being recorded",
+        )?;
+
+        registry.register(Box::new(notes_recorded_total.clone()))?;
+        registry.register(Box::new(notes_cleared_total.clone()))?;
+        registry.register(Box::new(keypresses_total.clone()))?;
+        registry.register(Box::new(recording_active.clone()))?;
+        registry.register(Box::new(current_note_len.clone()))?;
+
+        Ok(Self {
+            registry,
+            notes_recorded_total,
+            notes_cleared_total,
+            keypresses_total,
+            recording_active,
+            current_note_len,
+        })
+    }
+
+    /// Renders all registered metrics in Prometheus text exposition format.
+    pub fn render(&self) -> Result<String> {
+        let metric_families = self.registry.gather();
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&metric_families, &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}